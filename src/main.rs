@@ -1,17 +1,65 @@
-use clap::{Parser, ArgAction};
+use clap::{Parser, ArgAction, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use rust_cp::{total_size, CopyEngine, CopyOptions, PreserveOptions};
 use std::fs;
 use std::io;
-use std::path::Path;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// When to prompt before overwriting an existing destination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum InteractiveMode {
+    /// Never prompt; overwrite unconditionally.
+    Never,
+    /// Prompt once up front if the copy would overwrite several destinations,
+    /// then proceed without asking again.
+    Once,
+    /// Prompt before every overwrite.
+    Always,
+}
+
+/// How to name the backup of an existing destination before it is overwritten.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BackupMode {
+    /// Append `--suffix` to the existing destination's name.
+    Simple,
+    /// Append `.~N~`, choosing the next free integer N.
+    Numbered,
+}
+
+/// A single file attribute that `-p`/`--preserve` can carry over from source
+/// to destination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum PreserveAttr {
+    Mode,
+    Timestamps,
+    Ownership,
+}
+
+impl PreserveAttr {
+    fn to_options(attrs: &Option<Vec<PreserveAttr>>) -> PreserveOptions {
+        // Mode is carried over unconditionally, with or without `-p`, matching
+        // `fs::copy`'s historical behavior; timestamps/ownership are opt-in
+        // and only kick in when named explicitly.
+        let attrs = attrs.as_deref().unwrap_or(&[]);
+        PreserveOptions {
+            mode: true,
+            timestamps: attrs.contains(&PreserveAttr::Timestamps),
+            ownership: attrs.contains(&PreserveAttr::Ownership),
+        }
+    }
+}
 
 /// A tool that mimics `cp` command in Linux
 #[derive(Parser)]
 struct Cli {
-    /// Source file or directory path
-    source: String,
-
-    /// Destination file or directory path
-    destination: String,
+    /// Source file or directory path(s), followed by a destination. When
+    /// `-t`/`--target-directory` is used there is no trailing destination;
+    /// every positional argument is a source. clap can't express "a
+    /// variadic list, then one more required value" as two separate
+    /// positionals, so this single list is split by hand in `main`.
+    #[arg(required = true, value_name = "SOURCE... [DESTINATION]")]
+    source: Vec<String>,
 
     /// Copy directories recursively
     #[arg(short = 'r', long = "recursive", action = ArgAction::SetTrue)]
@@ -21,42 +69,291 @@ struct Cli {
     #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
     verbose: bool,
 
-    /// Interactive mode, prompt before overwriting files
-    #[arg(short = 'i', long = "interactive", action = ArgAction::SetTrue)]
-    interactive: bool,
+    /// Prompt before overwriting according to WHEN: never, once, or always.
+    /// Bare `-i`/`--interactive` is shorthand for `--interactive=always`.
+    #[arg(
+        short = 'i',
+        long = "interactive",
+        value_name = "WHEN",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "always",
+        require_equals = true
+    )]
+    interactive: Option<InteractiveMode>,
+
+    /// Never overwrite an existing destination; skip it silently
+    #[arg(short = 'n', long = "no-clobber", action = ArgAction::SetTrue, conflicts_with = "interactive")]
+    no_clobber: bool,
+
+    /// Copy all SOURCE arguments into DIRECTORY
+    #[arg(short = 't', long = "target-directory", value_name = "DIRECTORY")]
+    target_directory: Option<String>,
+
+    /// Treat DESTINATION as a normal file, even if it exists as a directory
+    #[arg(short = 'T', long = "no-target-directory", action = ArgAction::SetTrue, conflicts_with = "target_directory")]
+    no_target_directory: bool,
+
+    /// Show a progress bar while copying
+    #[arg(short = 'g', long = "progress", action = ArgAction::SetTrue)]
+    progress: bool,
+
+    /// Make a backup of each existing destination before overwriting it.
+    /// Takes an optional CONTROL: `simple` (default) or `numbered`.
+    #[arg(
+        short = 'b',
+        long = "backup",
+        value_name = "CONTROL",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "simple",
+        require_equals = true
+    )]
+    backup: Option<BackupMode>,
+
+    /// Suffix used for simple backups
+    #[arg(short = 'S', long = "suffix", value_name = "SUFFIX", default_value = "~")]
+    suffix: String,
+
+    /// Preserve file attributes. Takes an optional comma-separated
+    /// ATTR_LIST of `mode`, `timestamps`, `ownership` (default: all three).
+    #[arg(
+        short = 'p',
+        long = "preserve",
+        value_name = "ATTR_LIST",
+        value_enum,
+        num_args = 0..,
+        value_delimiter = ',',
+        default_missing_value = "mode,timestamps,ownership",
+        require_equals = true
+    )]
+    preserve: Option<Vec<PreserveAttr>>,
 }
 
 fn main() -> io::Result<()> {
     let args = Cli::parse();
 
-    let source_path = Path::new(&args.source);
-    let dest_path = Path::new(&args.destination);
+    // When `-t DIR` is used, every positional argument is a source. Otherwise
+    // the last positional argument is the destination and the rest are
+    // sources.
+    let target_directory = args.target_directory.clone();
 
-    // Check if source exists
-    if !source_path.exists() {
-        eprintln!("Source path does not exist: {}", args.source);
+    let (sources, destination) = match &target_directory {
+        Some(dir) => (args.source.clone(), dir.clone()),
+        None => {
+            if args.source.len() < 2 {
+                eprintln!("the following required arguments were not provided: <DESTINATION>");
+                std::process::exit(1);
+            }
+            let mut sources = args.source.clone();
+            let destination = sources.pop().unwrap();
+            (sources, destination)
+        }
+    };
+
+    let mut expanded_sources: Vec<PathBuf> = Vec::new();
+    for source in &sources {
+        expanded_sources.extend(expand_source(source)?);
+    }
+
+    let dest_path = Path::new(&destination);
+    let multiple_sources = expanded_sources.len() > 1 || target_directory.is_some();
+
+    if args.no_target_directory && multiple_sources {
+        eprintln!("Cannot combine --no-target-directory with multiple sources.");
+        std::process::exit(1);
+    }
+
+    if multiple_sources && !args.no_target_directory && !dest_path.is_dir() {
+        eprintln!("Target {} is not a directory.", dest_path.display());
         std::process::exit(1);
     }
 
-    if source_path.is_dir() {
-        // Handle directory copying with the recursive flag
-        if !args.recursive {
+    let mut jobs: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for source_path in &expanded_sources {
+        let source_path = source_path.as_path();
+
+        if !source_path.exists() {
+            eprintln!("Source path does not exist: {}", source_path.display());
+            std::process::exit(1);
+        }
+
+        if source_path.is_dir() && !args.recursive {
             eprintln!("Source is a directory. Use the -r flag to copy directories recursively.");
             std::process::exit(1);
         }
-        copy_directory(&source_path, &dest_path, &args)?;
+
+        // Join the source's basename onto the destination whenever the
+        // destination is being treated as a directory: either because there
+        // are multiple sources (already validated above to require an
+        // existing directory) or because a single source's destination
+        // happens to already exist as one.
+        let entry_dest = if !args.no_target_directory && (multiple_sources || dest_path.is_dir()) {
+            join_basename(dest_path, source_path)
+        } else {
+            dest_path.to_path_buf()
+        };
+
+        jobs.push((source_path.to_path_buf(), entry_dest));
+    }
+
+    let mut policy = args.interactive.unwrap_or(InteractiveMode::Never);
+    if policy == InteractiveMode::Once {
+        let (overwrite_count, dir_overwrite) = count_overwrites(&jobs);
+        if overwrite_count > 3 || dir_overwrite {
+            print!(
+                "This will overwrite {} existing destination(s). Continue? [y/N]: ",
+                overwrite_count
+            );
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            if !answer.trim().eq_ignore_ascii_case("y") {
+                println!("Aborted.");
+                return Ok(());
+            }
+        }
+        // Already confirmed up front; don't prompt again per file.
+        policy = InteractiveMode::Never;
+    }
+
+    let show_progress = args.progress && io::stdout().is_terminal();
+
+    let total: u64 = if show_progress {
+        jobs.iter().map(|(src, _)| total_size(src).unwrap_or(0)).sum()
+    } else {
+        0
+    };
+
+    let bar = if show_progress {
+        let bar = ProgressBar::new(total);
+        bar.set_style(
+            ProgressStyle::with_template(
+                "{msg} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})",
+            )
+            .unwrap(),
+        );
+        bar
     } else {
-        // Copy single file
-        copy_file(&source_path, &dest_path, &args)?;
+        ProgressBar::hidden()
+    };
+
+    let options = CopyOptions {
+        // Overwrite decisions (no-clobber, interactive, backup) are made by
+        // the CLI before handing a file to the engine, so the engine itself
+        // always overwrites once asked to copy a file.
+        overwrite: true,
+        preserve: PreserveAttr::to_options(&args.preserve),
+        ..CopyOptions::default()
+    };
+    let mut engine = CopyEngine::new(options, total);
+
+    for (source_path, entry_dest) in &jobs {
+        if source_path.is_dir() {
+            copy_directory(source_path, entry_dest, &args, policy, &bar, &mut engine)?;
+        } else {
+            copy_file(source_path, entry_dest, &args, policy, &bar, &mut engine)?;
+        }
     }
 
+    bar.finish_and_clear();
+
     Ok(())
 }
 
-/// Copy a single file with optional verbose and interactive modes
-fn copy_file(source: &Path, destination: &Path, args: &Cli) -> io::Result<()> {
+/// Join a source's basename onto a destination directory, as `cp` does when
+/// copying multiple sources into a target directory.
+fn join_basename(destination_dir: &Path, source: &Path) -> PathBuf {
+    let name = source.file_name().unwrap_or(source.as_os_str());
+    destination_dir.join(name)
+}
+
+/// Expand a source argument that may contain glob metacharacters (`*`, `?`, `[`)
+/// into the concrete paths it matches. Arguments with no metacharacters are
+/// passed through unchanged so plain paths behave exactly as before.
+fn expand_source(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    if !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+
+    let paths = glob::glob(pattern)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::other)?;
+
+    if paths.is_empty() {
+        eprintln!("Source pattern matched no files: {}", pattern);
+        std::process::exit(1);
+    }
+
+    Ok(paths)
+}
+
+/// Count how many planned copies would overwrite an existing destination, for
+/// the one-time confirmation used by `--interactive=once`. Returns the number
+/// of existing plain-file destinations and whether any destination directory
+/// already exists (and so would receive overwritten children).
+fn count_overwrites(jobs: &[(PathBuf, PathBuf)]) -> (usize, bool) {
+    let mut file_overwrites = 0;
+    let mut dir_overwrite = false;
+    for (source, destination) in jobs {
+        if source.is_dir() {
+            if destination.exists() {
+                dir_overwrite = true;
+            }
+        } else if destination.exists() {
+            file_overwrites += 1;
+        }
+    }
+    (file_overwrites, dir_overwrite)
+}
+
+/// Rename an existing destination out of the way before it gets overwritten,
+/// returning the path it was moved to.
+fn backup_destination(destination: &Path, mode: BackupMode, suffix: &str) -> io::Result<PathBuf> {
+    let backup_path = match mode {
+        BackupMode::Simple => {
+            let mut name = destination.as_os_str().to_os_string();
+            name.push(suffix);
+            PathBuf::from(name)
+        }
+        BackupMode::Numbered => {
+            let mut n = 1;
+            loop {
+                let mut name = destination.as_os_str().to_os_string();
+                name.push(format!(".~{}~", n));
+                let candidate = PathBuf::from(name);
+                if !candidate.exists() {
+                    break candidate;
+                }
+                n += 1;
+            }
+        }
+    };
+
+    fs::rename(destination, &backup_path)?;
+    Ok(backup_path)
+}
+
+/// Copy a single file, applying the CLI's overwrite decisions (no-clobber,
+/// interactive confirmation, backup) before handing the mechanical copy off
+/// to the [`CopyEngine`].
+fn copy_file(
+    source: &Path,
+    destination: &Path,
+    args: &Cli,
+    policy: InteractiveMode,
+    bar: &ProgressBar,
+    engine: &mut CopyEngine,
+) -> io::Result<()> {
+    let exists = destination.exists();
+
+    if args.no_clobber && exists {
+        return Ok(());
+    }
+
     // Handle interactive mode: confirm overwrite
-    if args.interactive && destination.exists() {
+    if policy == InteractiveMode::Always && exists {
         print!("Overwrite {}? [y/N]: ", destination.display());
         io::stdout().flush()?;
         let mut answer = String::new();
@@ -67,8 +364,19 @@ fn copy_file(source: &Path, destination: &Path, args: &Cli) -> io::Result<()> {
         }
     }
 
-    // Perform the file copy
-    fs::copy(source, destination)?;
+    if let Some(mode) = args.backup {
+        if exists {
+            let backup_path = backup_destination(destination, mode, &args.suffix)?;
+            if args.verbose {
+                println!("Backed up {} to {}", destination.display(), backup_path.display());
+            }
+        }
+    }
+
+    engine.copy_file(source, destination, &mut |progress| {
+        bar.set_message(progress.file_name.clone());
+        bar.set_position(progress.copied_bytes);
+    })?;
 
     if args.verbose {
         println!("Copied {} to {}", source.display(), destination.display());
@@ -77,8 +385,16 @@ fn copy_file(source: &Path, destination: &Path, args: &Cli) -> io::Result<()> {
     Ok(())
 }
 
-/// Recursively copy directories with optional verbose and interactive modes
-fn copy_directory(source: &Path, destination: &Path, args: &Cli) -> io::Result<()> {
+/// Recursively copy directories, applying the CLI's overwrite decisions to
+/// each file along the way.
+fn copy_directory(
+    source: &Path,
+    destination: &Path,
+    args: &Cli,
+    policy: InteractiveMode,
+    bar: &ProgressBar,
+    engine: &mut CopyEngine,
+) -> io::Result<()> {
     // Create the destination directory if it doesn't exist
     if !destination.exists() {
         fs::create_dir_all(destination)?;
@@ -92,16 +408,148 @@ fn copy_directory(source: &Path, destination: &Path, args: &Cli) -> io::Result<(
 
         if entry_path.is_dir() {
             // Recursive call for sub-directories
-            copy_directory(&entry_path, &dest_entry_path, args)?;
+            copy_directory(&entry_path, &dest_entry_path, args, policy, bar, engine)?;
         } else {
             // Copy individual files
-            copy_file(&entry_path, &dest_entry_path, args)?;
+            copy_file(&entry_path, &dest_entry_path, args, policy, bar, engine)?;
         }
     }
 
+    // Copying children just now bumped this directory's mtime; restore it
+    // now that all of them are done.
+    engine.restore_dir_timestamps(source, destination)?;
+
     if args.verbose {
         println!("Recursively copied directory {} to {}", source.display(), destination.display());
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// A fresh scratch directory under the OS temp dir, unique per test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let dir = std::env::temp_dir().join(format!("rust_cp_test_{}_{}_{}", name, std::process::id(), nanos));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn join_basename_joins_the_sources_file_name_onto_the_destination_dir() {
+        let dest_dir = Path::new("/tmp/some/dest");
+        let source = Path::new("/home/user/file.txt");
+
+        assert_eq!(join_basename(dest_dir, source), dest_dir.join("file.txt"));
+    }
+
+    #[test]
+    fn expand_source_passes_through_patterns_with_no_metacharacters() {
+        let dir = scratch_dir("expand_source_plain");
+        let file = dir.join("a.txt");
+        fs::write(&file, b"hi").unwrap();
+
+        // No glob metacharacters, so the path is returned as-is without
+        // touching the filesystem (it need not even exist).
+        let missing = dir.join("missing.txt");
+        assert_eq!(
+            expand_source(missing.to_str().unwrap()).unwrap(),
+            vec![missing.clone()]
+        );
+    }
+
+    #[test]
+    fn to_options_preserves_mode_unconditionally_without_any_flag() {
+        let options = PreserveAttr::to_options(&None);
+
+        assert!(options.mode);
+        assert!(!options.timestamps);
+        assert!(!options.ownership);
+    }
+
+    #[test]
+    fn to_options_enables_only_the_named_attributes_besides_mode() {
+        let options = PreserveAttr::to_options(&Some(vec![PreserveAttr::Timestamps]));
+
+        assert!(options.mode);
+        assert!(options.timestamps);
+        assert!(!options.ownership);
+    }
+
+    #[test]
+    fn count_overwrites_counts_existing_file_destinations() {
+        let dir = scratch_dir("count_overwrites_files");
+        let existing = dir.join("existing.txt");
+        fs::write(&existing, b"old").unwrap();
+        let missing = dir.join("missing.txt");
+
+        let jobs = vec![
+            (dir.join("source_a.txt"), existing),
+            (dir.join("source_b.txt"), missing),
+        ];
+
+        assert_eq!(count_overwrites(&jobs), (1, false));
+    }
+
+    #[test]
+    fn count_overwrites_flags_an_existing_destination_directory() {
+        let dir = scratch_dir("count_overwrites_dir");
+        let source_dir = dir.join("source_dir");
+        fs::create_dir_all(&source_dir).unwrap();
+        let dest_dir = dir.join("dest_dir");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let jobs = vec![(source_dir, dest_dir)];
+
+        assert_eq!(count_overwrites(&jobs), (0, true));
+    }
+
+    #[test]
+    fn expand_source_expands_a_glob_to_its_matches() {
+        let dir = scratch_dir("expand_source_glob");
+        fs::write(dir.join("a.txt"), b"a").unwrap();
+        fs::write(dir.join("b.txt"), b"b").unwrap();
+        fs::write(dir.join("c.log"), b"c").unwrap();
+
+        let pattern = dir.join("*.txt");
+        let mut matches = expand_source(pattern.to_str().unwrap()).unwrap();
+        matches.sort();
+
+        assert_eq!(matches, vec![dir.join("a.txt"), dir.join("b.txt")]);
+    }
+
+    #[test]
+    fn numbered_backup_picks_the_next_free_suffix() {
+        let dir = scratch_dir("numbered_backup");
+        let dest = dir.join("file.txt");
+        fs::write(&dest, b"v1").unwrap();
+
+        let first = backup_destination(&dest, BackupMode::Numbered, "~").unwrap();
+        assert_eq!(first, dir.join("file.txt.~1~"));
+        assert!(!dest.exists());
+        assert_eq!(fs::read(&first).unwrap(), b"v1");
+
+        // Recreate the destination and back it up again: `.~1~` is now taken,
+        // so the next free suffix should be `.~2~`.
+        fs::write(&dest, b"v2").unwrap();
+        let second = backup_destination(&dest, BackupMode::Numbered, "~").unwrap();
+        assert_eq!(second, dir.join("file.txt.~2~"));
+        assert_eq!(fs::read(&second).unwrap(), b"v2");
+    }
+
+    #[test]
+    fn simple_backup_appends_the_suffix() {
+        let dir = scratch_dir("simple_backup");
+        let dest = dir.join("file.txt");
+        fs::write(&dest, b"content").unwrap();
+
+        let backup = backup_destination(&dest, BackupMode::Simple, "~").unwrap();
+        assert_eq!(backup, dir.join("file.txt~"));
+        assert!(!dest.exists());
+        assert_eq!(fs::read(&backup).unwrap(), b"content");
+    }
+}